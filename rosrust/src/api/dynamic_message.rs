@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use rosrust_codegen::dynamic::{DynamicMessage, MessageSchema};
+
+use super::error::Result;
+use super::raw_message::RawPublisherStream;
+
+/// Publisher handle returned by [`super::ros::Ros::publish_dynamic`]:
+/// sends a [`DynamicMessage`] by encoding it to the wire format and
+/// forwarding it through the same raw publication machinery used by
+/// `publish_raw`.
+pub struct DynamicPublisherStream {
+    schema: Arc<MessageSchema>,
+    stream: RawPublisherStream,
+}
+
+impl DynamicPublisherStream {
+    pub(crate) fn new(schema: Arc<MessageSchema>, stream: RawPublisherStream) -> DynamicPublisherStream {
+        DynamicPublisherStream { schema: schema, stream: stream }
+    }
+
+    pub fn schema(&self) -> &Arc<MessageSchema> {
+        &self.schema
+    }
+
+    pub fn send(&self, message: &DynamicMessage) -> Result<()> {
+        let bytes = message.encode()?;
+        self.stream.send(&bytes)
+    }
+}
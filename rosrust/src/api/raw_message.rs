@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use super::error::Result;
+use super::slave::RawPublication;
+
+/// An incoming message that has not been deserialized into a concrete
+/// Rust type yet. Used by [`super::ros::Ros::subscribe_raw`] and by
+/// anything (such as `record::Recorder`) that only needs to move bytes
+/// around without knowing the message type at compile time.
+#[derive(Clone, Debug)]
+pub struct RawMessage {
+    pub data: Vec<u8>,
+    pub connection_header: HashMap<String, String>,
+}
+
+impl RawMessage {
+    pub fn msg_type(&self) -> Option<&str> {
+        self.connection_header.get("type").map(String::as_str)
+    }
+
+    pub fn md5sum(&self) -> Option<&str> {
+        self.connection_header.get("md5sum").map(String::as_str)
+    }
+}
+
+/// A publisher handle for pre-encoded, wire-format message bodies. The
+/// counterpart to [`RawMessage`] on the publishing side.
+pub struct RawPublisherStream {
+    publication: RawPublication,
+}
+
+impl RawPublisherStream {
+    pub(crate) fn new(publication: RawPublication) -> RawPublisherStream {
+        RawPublisherStream { publication }
+    }
+
+    pub fn send(&self, payload: &[u8]) -> Result<()> {
+        self.publication.publish(payload)
+    }
+}
@@ -3,10 +3,17 @@ use super::master::{self, Master, Topic};
 use super::slave::Slave;
 use super::error::{ErrorKind, Result};
 use super::super::rosxmlrpc::Response;
+use super::super::bridge::Bridge;
+use super::super::reconnect::{self, MasterStatus, ReconnectPolicy, Registrations};
+use super::super::queue::{BoundedPublisherStream, QueuedSubscription, SubscribeOptions, Subscriptions, WorkerPool};
+use super::dynamic_message::DynamicPublisherStream;
 use super::naming::{self, Resolver};
+use super::raw_message::{RawMessage, RawPublisherStream};
 use super::resolve;
+use rosrust_codegen::dynamic::{self as dynamic_msg, DynamicMessage};
 use tcpros::{Client, Message, PublisherStream, ServicePair, ServiceResult};
 use xml_rpc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 pub struct Ros {
@@ -15,8 +22,16 @@ pub struct Ros {
     hostname: String,
     resolver: Resolver,
     name: String,
+    registrations: Arc<Mutex<Registrations>>,
+    subscriptions: Arc<Subscriptions>,
+    worker_pool: Arc<WorkerPool>,
 }
 
+/// Number of threads backing the worker pool that dispatches callbacks
+/// registered through `subscribe_with_opts`, shared across every
+/// subscription on a node.
+const DEFAULT_WORKER_POOL_SIZE: usize = 4;
+
 impl Ros {
     pub fn new(name: &str) -> Result<Ros> {
         let namespace = resolve::namespace();
@@ -50,6 +65,9 @@ impl Ros {
             hostname: String::from(hostname),
             resolver: resolver,
             name: name,
+            registrations: Arc::new(Mutex::new(Registrations::default())),
+            subscriptions: Arc::new(Subscriptions::default()),
+            worker_pool: Arc::new(WorkerPool::new(DEFAULT_WORKER_POOL_SIZE)),
         })
     }
 
@@ -142,6 +160,7 @@ impl Ros {
             self.master.unregister_service(&name, &api)?;
             Err(err.into())
         } else {
+            self.registrations.lock().unwrap().add_service(&name, &api);
             Ok(())
         }
 
@@ -168,6 +187,10 @@ impl Ros {
                         err
                     );
                 }
+                self.registrations.lock().unwrap().add_subscriber(
+                    &name,
+                    &T::msg_type(),
+                );
                 Ok(())
             }
             Err(err) => {
@@ -185,7 +208,82 @@ impl Ros {
         let name = self.resolver.translate(topic)?;
         let stream = self.slave.add_publication::<T>(&self.hostname, &name)?;
         match self.master.register_publisher(&name, &T::msg_type()) {
-            Ok(_) => Ok(stream),
+            Ok(_) => {
+                self.registrations.lock().unwrap().add_publisher(
+                    &name,
+                    &T::msg_type(),
+                );
+                Ok(stream)
+            }
+            Err(error) => {
+                error!(
+                    "Failed to register publisher for topic '{}': {}",
+                    name,
+                    error
+                );
+                self.slave.remove_publication(&name);
+                self.master.unregister_publisher(&name)?;
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Subscribes without deserializing into a concrete message type,
+    /// handing the callback the raw wire-format bytes and connection
+    /// header instead. Intended for tools like `record::Recorder` that
+    /// need to move messages around without the type compiled in.
+    pub fn subscribe_raw<F>(&mut self, topic: &str, msg_type: &str, callback: F) -> Result<()>
+    where
+        F: Fn(RawMessage) -> () + Send + 'static,
+    {
+        let name = self.resolver.translate(topic)?;
+        self.slave.add_raw_subscription(&name, callback)?;
+
+        match self.master.register_subscriber(&name, msg_type) {
+            Ok(publishers) => {
+                if let Err(err) = self.slave.add_publishers_to_subscription(
+                    &name,
+                    publishers.into_iter(),
+                )
+                {
+                    error!(
+                        "Failed to subscribe to all publishers of topic '{}': {}",
+                        name,
+                        err
+                    );
+                }
+                self.registrations.lock().unwrap().add_subscriber(&name, msg_type);
+                Ok(())
+            }
+            Err(err) => {
+                self.slave.remove_subscription(&name);
+                self.master.unregister_subscriber(&name)?;
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Publishes raw, already wire-encoded bytes under a given type name
+    /// and md5sum, without the concrete message type compiled in. Used by
+    /// `record::Player` to replay a capture without regenerated structs.
+    pub fn publish_raw(
+        &mut self,
+        topic: &str,
+        msg_type: &str,
+        md5sum: &str,
+    ) -> Result<RawPublisherStream> {
+        let name = self.resolver.translate(topic)?;
+        let stream = self.slave.add_raw_publication(
+            &self.hostname,
+            &name,
+            msg_type,
+            md5sum,
+        )?;
+        match self.master.register_publisher(&name, msg_type) {
+            Ok(_) => {
+                self.registrations.lock().unwrap().add_publisher(&name, msg_type);
+                Ok(stream)
+            }
             Err(error) => {
                 error!(
                     "Failed to register publisher for topic '{}': {}",
@@ -198,6 +296,146 @@ impl Ros {
             }
         }
     }
+
+    /// Like [`subscribe`](Ros::subscribe), but buffers incoming messages
+    /// in a bounded, policy-driven queue and dispatches `callback` on a
+    /// worker pool shared across every subscription on this node, rather
+    /// than on a dedicated per-subscription thread.
+    pub fn subscribe_with_opts<T, F>(
+        &mut self,
+        topic: &str,
+        opts: SubscribeOptions,
+        callback: F,
+    ) -> Result<()>
+    where
+        T: Message,
+        F: Fn(T) -> () + Send + Sync + 'static,
+    {
+        let name = self.resolver.translate(topic)?;
+        let dropped = self.subscriptions.counter(&name);
+        let subscription = QueuedSubscription::new(opts, dropped, self.worker_pool.clone(), callback);
+        self.slave.add_subscription::<T, _>(
+            &name,
+            move |msg| subscription.push(msg),
+        )?;
+
+        match self.master.register_subscriber(&name, &T::msg_type()) {
+            Ok(publishers) => {
+                if let Err(err) = self.slave.add_publishers_to_subscription(
+                    &name,
+                    publishers.into_iter(),
+                )
+                {
+                    error!(
+                        "Failed to subscribe to all publishers of topic '{}': {}",
+                        name,
+                        err
+                    );
+                }
+                self.registrations.lock().unwrap().add_subscriber(
+                    &name,
+                    &T::msg_type(),
+                );
+                Ok(())
+            }
+            Err(err) => {
+                self.slave.remove_subscription(&name);
+                self.master.unregister_subscriber(&name)?;
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Number of messages dropped so far by a `subscribe_with_opts`
+    /// queue's overflow policy on `topic`.
+    pub fn dropped_messages(&self, topic: &str) -> usize {
+        self.subscriptions.dropped(topic)
+    }
+
+    /// Like [`publish`](Ros::publish), but outbound messages pass through
+    /// a bounded queue before being sent, so a slow connection applies
+    /// the configured overflow policy; only `OverflowPolicy::Block` makes
+    /// the caller wait, and only once the queue is full.
+    pub fn publish_with_queue<T>(
+        &mut self,
+        topic: &str,
+        opts: SubscribeOptions,
+    ) -> Result<BoundedPublisherStream<T>>
+    where
+        T: Message + Send + 'static,
+    {
+        let stream = self.publish::<T>(topic)?;
+        Ok(BoundedPublisherStream::new(stream, opts))
+    }
+
+    /// Subscribes to `topic` without the message type compiled in,
+    /// resolving `type_name`'s definition from `ROSRUST_MSG_PATH`/
+    /// `CMAKE_PREFIX_PATH` at call time and handing the callback a
+    /// [`DynamicMessage`] decoded field-by-field from the wire bytes.
+    pub fn subscribe_dynamic<F>(&mut self, topic: &str, type_name: &str, callback: F) -> Result<()>
+    where
+        F: Fn(DynamicMessage) -> () + Send + 'static,
+    {
+        let folders = dynamic_msg::resolve_search_paths();
+        let folder_refs = folders.iter().map(String::as_str).collect::<Vec<&str>>();
+        let schema = dynamic_msg::resolve(&folder_refs, type_name)?;
+        let msg_type = schema.msg_type.clone();
+        let topic_owned = topic.to_owned();
+
+        self.subscribe_raw(topic, &msg_type, move |raw| {
+            match DynamicMessage::decode(schema.clone(), &raw.data) {
+                Ok(message) => callback(message),
+                Err(err) => error!(
+                    "Failed to decode dynamic message on '{}': {}",
+                    topic_owned,
+                    err
+                ),
+            }
+        })
+    }
+
+    /// Publishes `type_name` on `topic` without the message type compiled
+    /// in, resolving its definition and md5sum the same way
+    /// `subscribe_dynamic` does.
+    pub fn publish_dynamic(&mut self, topic: &str, type_name: &str) -> Result<DynamicPublisherStream> {
+        let folders = dynamic_msg::resolve_search_paths();
+        let folder_refs = folders.iter().map(String::as_str).collect::<Vec<&str>>();
+        let schema = dynamic_msg::resolve(&folder_refs, type_name)?;
+        let stream = self.publish_raw(topic, &schema.msg_type, &schema.md5sum)?;
+        Ok(DynamicPublisherStream::new(schema, stream))
+    }
+
+    /// Starts a JSON bridge on `addr`, letting non-ROS clients subscribe,
+    /// publish, and call services via a small `{"op": ...}` protocol.
+    ///
+    /// This hands the node over to the bridge, since incoming requests
+    /// need ongoing access to `subscribe`/`publish`/`client`/`service` for
+    /// as long as the bridge is running. Message types have to be made
+    /// available with [`Bridge::register`] before clients can use them.
+    pub fn start_bridge(self, addr: &str) -> Result<Bridge> {
+        Bridge::new(Arc::new(Mutex::new(self)), addr)
+    }
+
+    /// Starts a background supervisor that periodically pings the master
+    /// and, on detecting that it restarted or came back after a drop,
+    /// replays every `register_publisher`/`register_subscriber`/
+    /// `register_service` call this node has made so far. `on_status` is
+    /// called after every ping: `Connected` on each successful ping with
+    /// nothing to replay, `Lost` once when the master becomes unreachable,
+    /// and `Reestablished` after registrations have been replayed.
+    pub fn set_master_reconnect<F>(&self, policy: ReconnectPolicy, on_status: F)
+    where
+        F: Fn(MasterStatus) + Send + 'static,
+    {
+        reconnect::spawn(
+            self.master.clone(),
+            self.slave.clone(),
+            self.name.clone(),
+            self.registrations.clone(),
+            policy,
+            on_status,
+        );
+    }
 }
 
 pub struct Parameter<'a> {
@@ -0,0 +1,52 @@
+use serde::Serialize;
+use serde_json::Value;
+use tcpros::Message;
+
+/// Wire format for the bridge's JSON protocol. Clients speak `op`-tagged
+/// envelopes; `subscribe` results are pushed back using the `publish`
+/// variant so a single message type can be (de)serialized with one impl.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Envelope {
+    Subscribe {
+        topic: String,
+        #[serde(rename = "type")]
+        msg_type: String,
+    },
+    Publish {
+        topic: String,
+        #[serde(rename = "type", default)]
+        msg_type: String,
+        msg: Value,
+    },
+    CallService {
+        service: String,
+        #[serde(rename = "type", default)]
+        msg_type: String,
+        args: Value,
+    },
+    ServiceResponse {
+        service: String,
+        #[serde(rename = "type", default)]
+        msg_type: String,
+        values: Value,
+    },
+}
+
+impl Envelope {
+    pub fn publish_to<T: Message + Serialize>(topic: &str, msg: &T) -> Envelope {
+        Envelope::Publish {
+            topic: topic.into(),
+            msg_type: T::msg_type(),
+            msg: serde_json::to_value(msg).unwrap_or(Value::Null),
+        }
+    }
+
+    pub fn service_response(service: &str, msg_type: &str, values: Value) -> Envelope {
+        Envelope::ServiceResponse {
+            service: service.into(),
+            msg_type: msg_type.into(),
+            values,
+        }
+    }
+}
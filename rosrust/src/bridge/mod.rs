@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use serde_json::Value;
+
+use super::api::error::{ErrorKind, Result};
+use super::api::ros::Ros;
+use tcpros::{Message, ServicePair};
+
+mod envelope;
+
+use self::envelope::Envelope;
+
+type SubscribeFn = Box<Fn(&Arc<Mutex<Ros>>, &str, Arc<Mutex<TcpStream>>) -> Result<()> + Send + Sync>;
+type PublishFn = Box<Fn(&Arc<Mutex<Ros>>, &str) -> Result<Box<Fn(Value) -> Result<()> + Send>> + Send + Sync>;
+type ServiceFn = Box<Fn(&Arc<Mutex<Ros>>, &str, Value) -> Result<Value> + Send + Sync>;
+
+struct Converter {
+    subscribe: SubscribeFn,
+    publish: PublishFn,
+}
+
+/// Maps a ROS message type name (e.g. `"sensor_msgs/Imu"`) to the closures
+/// needed to move that type across the JSON boundary. The concrete type is
+/// only known where `register::<T>()` is called; dispatch afterwards works
+/// purely off the `msg_type()` string carried in the envelope.
+///
+/// Services are kept in a separate map from topics: a `ServicePair` has no
+/// `Message` impl of its own, only a `Request`/`Response` pair, so a service
+/// call is multiplexed on `msg_type()` exactly like `subscribe`/`publish`
+/// but through `register_service::<T>()` instead.
+///
+/// `publishers` caches one live publish closure per *topic* (as opposed to
+/// `converters`, which is keyed by message type and only builds one): the
+/// closure in `Converter::publish` advertises with the master and opens a
+/// fresh `PublisherStream` every time it's called, so calling it once per
+/// `{"op":"publish"}` envelope would re-advertise on every message. This
+/// mirrors how `record::Player::play` caches one stream per topic instead
+/// of re-advertising per frame.
+#[derive(Default)]
+struct Registry {
+    converters: Mutex<HashMap<String, Converter>>,
+    services: Mutex<HashMap<String, ServiceFn>>,
+    publishers: Mutex<HashMap<String, Box<Fn(Value) -> Result<()> + Send>>>,
+}
+
+impl Registry {
+    fn register<T>(&self)
+    where
+        T: Message + Serialize + DeserializeOwned + Send + 'static,
+    {
+        let subscribe: SubscribeFn = Box::new(|ros, topic, out| {
+            let out = out.clone();
+            let topic = topic.to_owned();
+            ros.lock().unwrap().subscribe::<T, _>(&topic, move |msg| {
+                let envelope = Envelope::publish_to(&topic, &msg);
+                if let Ok(line) = serde_json::to_string(&envelope) {
+                    let mut stream = out.lock().unwrap();
+                    let _ = writeln!(stream, "{}", line);
+                }
+            })
+        });
+        let publish: PublishFn = Box::new(|ros, topic| {
+            let stream = ros.lock().unwrap().publish::<T>(topic)?;
+            let callback = move |payload: Value| -> Result<()> {
+                let msg: T = serde_json::from_value(payload).map_err(ErrorKind::from)?;
+                stream.send(msg).map_err(ErrorKind::from)?;
+                Ok(())
+            };
+            Ok(Box::new(callback) as Box<Fn(Value) -> Result<()> + Send>)
+        });
+        self.converters.lock().unwrap().insert(
+            T::msg_type(),
+            Converter { subscribe, publish },
+        );
+    }
+
+    fn register_service<T>(&self)
+    where
+        T: ServicePair + Send + 'static,
+        T::Request: DeserializeOwned,
+        T::Response: Serialize,
+    {
+        let call: ServiceFn = Box::new(|ros, service, args| {
+            let client = ros.lock().unwrap().client::<T>(service)?;
+            let request: T::Request = serde_json::from_value(args).map_err(ErrorKind::from)?;
+            let response = client.req(&request).map_err(ErrorKind::from)?;
+            serde_json::to_value(&response).map_err(|err| ErrorKind::from(err).into())
+        });
+        self.services.lock().unwrap().insert(T::msg_type(), call);
+    }
+
+    /// Publishes `payload` on `topic`, advertising and caching a publish
+    /// closure for `topic` the first time it's seen and reusing it on
+    /// every later call instead of re-advertising with the master.
+    fn publish(&self, ros: &Arc<Mutex<Ros>>, topic: &str, msg_type: &str, payload: Value) -> Result<()> {
+        {
+            let publishers = self.publishers.lock().unwrap();
+            if let Some(publish) = publishers.get(topic) {
+                return publish(payload);
+            }
+        }
+        // Advertising can block on a master round-trip, so it happens
+        // without holding the `publishers` lock; two concurrent first
+        // publishes on the same new topic can race and each advertise
+        // once, but only one closure ends up cached.
+        let publish = {
+            let converters = self.converters.lock().unwrap();
+            let converter = converters.get(msg_type).ok_or_else(|| {
+                ErrorKind::BridgeError(format!("No converter registered for '{}'", msg_type))
+            })?;
+            (converter.publish)(ros, topic)?
+        };
+        let result = publish(payload);
+        self.publishers.lock().unwrap().insert(topic.to_owned(), publish);
+        result
+    }
+}
+
+/// A running JSON gateway that lets non-ROS clients subscribe, publish,
+/// and call services by name. The wire format is newline-delimited JSON
+/// over a plain TCP socket (one `{"op": ...}` envelope per line) — there
+/// is no WebSocket handshake or framing, so this is not a drop-in
+/// replacement for rosbridge's browser-facing protocol.
+///
+/// Every message and service type the bridge needs to move across the
+/// wire must be registered up front, since the concrete Rust type is
+/// only known at compile time:
+///
+/// ```no_run
+/// let mut ros = rosrust::Ros::new("bridge_node")?;
+/// let bridge = ros.start_bridge("0.0.0.0:9099")?;
+/// bridge.register::<sensor_msgs::Imu>();
+/// bridge.register_service::<rospy_tutorials::AddTwoInts>();
+/// ```
+pub struct Bridge {
+    ros: Arc<Mutex<Ros>>,
+    registry: Arc<Registry>,
+}
+
+impl Bridge {
+    pub(crate) fn new(ros: Arc<Mutex<Ros>>, addr: &str) -> Result<Bridge> {
+        let registry = Arc::new(Registry::default());
+        let listener = TcpListener::bind(addr).map_err(ErrorKind::from)?;
+
+        let bridge = Bridge {
+            ros: ros.clone(),
+            registry: registry.clone(),
+        };
+
+        thread::spawn(move || for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Bridge listener failed to accept connection: {}", err);
+                    continue;
+                }
+            };
+            let ros = ros.clone();
+            let registry = registry.clone();
+            thread::spawn(move || handle_connection(stream, &ros, &registry));
+        });
+
+        Ok(bridge)
+    }
+
+    /// Makes a concrete message type available to the bridge under its
+    /// `msg_type()` name, so `subscribe`/`publish` envelopes naming that
+    /// type can be dispatched at runtime.
+    pub fn register<T>(&self)
+    where
+        T: Message + Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.registry.register::<T>();
+    }
+
+    /// Makes a concrete service type available to the bridge under its
+    /// `msg_type()` name, so `call_service` envelopes naming that type can
+    /// be dispatched at runtime.
+    pub fn register_service<T>(&self)
+    where
+        T: ServicePair + Send + 'static,
+        T::Request: DeserializeOwned,
+        T::Response: Serialize,
+    {
+        self.registry.register_service::<T>();
+    }
+}
+
+fn handle_connection(stream: TcpStream, ros: &Arc<Mutex<Ros>>, registry: &Arc<Registry>) {
+    let out = Arc::new(Mutex::new(stream.try_clone().expect(
+        "Failed to clone bridge connection for writing",
+    )));
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                error!("Bridge connection read failed: {}", err);
+                return;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Err(err) = dispatch(&line, ros, registry, &out) {
+            error!("Bridge failed to handle request '{}': {}", line, err);
+        }
+    }
+}
+
+fn dispatch(
+    line: &str,
+    ros: &Arc<Mutex<Ros>>,
+    registry: &Arc<Registry>,
+    out: &Arc<Mutex<TcpStream>>,
+) -> Result<()> {
+    let envelope: Envelope = serde_json::from_str(line).map_err(ErrorKind::from)?;
+    match envelope {
+        Envelope::Subscribe { topic, msg_type } => {
+            let converters = registry.converters.lock().unwrap();
+            let converter = converters.get(&msg_type).ok_or_else(|| {
+                ErrorKind::BridgeError(format!("No converter registered for '{}'", msg_type))
+            })?;
+            (converter.subscribe)(ros, &topic, out.clone())
+        }
+        Envelope::Publish { topic, msg_type, msg } => registry.publish(ros, &topic, &msg_type, msg),
+        Envelope::CallService { service, msg_type, args } => {
+            let result = {
+                let services = registry.services.lock().unwrap();
+                let call = services.get(&msg_type).ok_or_else(|| {
+                    ErrorKind::BridgeError(format!("No service registered for '{}'", msg_type))
+                })?;
+                call(ros, &service, args)?
+            };
+            let envelope = Envelope::service_response(&service, &msg_type, result);
+            let line = serde_json::to_string(&envelope).map_err(ErrorKind::from)?;
+            let mut stream = out.lock().unwrap();
+            writeln!(stream, "{}", line).map_err(ErrorKind::from)?;
+            Ok(())
+        }
+    }
+}
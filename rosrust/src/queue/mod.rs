@@ -0,0 +1,370 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use threadpool::ThreadPool;
+
+use tcpros::{Message, PublisherStream};
+
+/// What to do with an incoming message once its queue is already full,
+/// mirroring ROS's own `queue_size` semantics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    DropOldest,
+    DropNewest,
+    Block,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SubscribeOptions {
+    pub queue_size: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl SubscribeOptions {
+    /// A queue of `queue_size` messages that drops the oldest buffered
+    /// message once full, which is ROS's default `queue_size` behavior.
+    /// `queue_size` of zero means unbounded.
+    pub fn new(queue_size: usize) -> SubscribeOptions {
+        SubscribeOptions {
+            queue_size: queue_size,
+            policy: OverflowPolicy::DropOldest,
+        }
+    }
+
+    pub fn with_policy(mut self, policy: OverflowPolicy) -> SubscribeOptions {
+        self.policy = policy;
+        self
+    }
+}
+
+struct BoundedQueue<T> {
+    items: Mutex<VecDeque<T>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl<T> BoundedQueue<T> {
+    fn new(capacity: usize, policy: OverflowPolicy, dropped: Arc<AtomicUsize>) -> BoundedQueue<T> {
+        BoundedQueue {
+            items: Mutex::new(VecDeque::new()),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity: capacity,
+            policy: policy,
+            dropped: dropped,
+        }
+    }
+
+    fn push(&self, item: T) {
+        let mut items = self.items.lock().unwrap();
+        if self.capacity == 0 {
+            items.push_back(item);
+            self.not_empty.notify_one();
+            return;
+        }
+        loop {
+            if items.len() < self.capacity {
+                items.push_back(item);
+                self.not_empty.notify_one();
+                return;
+            }
+            match self.policy {
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                OverflowPolicy::DropOldest => {
+                    items.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    items.push_back(item);
+                    self.not_empty.notify_one();
+                    return;
+                }
+                OverflowPolicy::Block => {
+                    items = self.not_full.wait(items).unwrap();
+                }
+            }
+        }
+    }
+
+    fn pop(&self) -> T {
+        let mut items = self.items.lock().unwrap();
+        loop {
+            if let Some(item) = items.pop_front() {
+                self.not_full.notify_one();
+                return item;
+            }
+            items = self.not_empty.wait(items).unwrap();
+        }
+    }
+}
+
+/// Counts free slots in the shared [`WorkerPool`], so submitting a job can
+/// block until one is actually available instead of piling up in
+/// `ThreadPool`'s own unbounded internal queue, which would otherwise let
+/// a slow handler silently defeat every subscription's `queue_size`.
+struct Semaphore {
+    permits: Mutex<usize>,
+    available: Condvar,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: Mutex::new(permits),
+            available: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) {
+        let mut permits = self.permits.lock().unwrap();
+        while *permits == 0 {
+            permits = self.available.wait(permits).unwrap();
+        }
+        *permits -= 1;
+    }
+
+    fn release(&self) {
+        let mut permits = self.permits.lock().unwrap();
+        *permits += 1;
+        self.available.notify_one();
+    }
+}
+
+/// A shared thread pool that subscription callbacks are dispatched onto,
+/// so a slow handler on one topic doesn't spin up an unbounded number of
+/// OS threads across a node with many subscriptions.
+///
+/// `execute` blocks the caller until a worker thread is actually free,
+/// rather than handing off to `ThreadPool`'s own unbounded job queue; that
+/// keeps backpressure from a slow handler visible at each subscription's
+/// [`BoundedQueue`] instead of it draining straight into the pool.
+pub struct WorkerPool {
+    pool: Mutex<ThreadPool>,
+    available: Arc<Semaphore>,
+}
+
+impl WorkerPool {
+    pub fn new(size: usize) -> WorkerPool {
+        WorkerPool {
+            pool: Mutex::new(ThreadPool::new(size)),
+            available: Arc::new(Semaphore::new(size)),
+        }
+    }
+
+    fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        self.available.acquire();
+        let available = self.available.clone();
+        self.pool.lock().unwrap().execute(move || {
+            job();
+            available.release();
+        });
+    }
+}
+
+/// Tracks, per topic, how many buffered messages have been dropped by a
+/// bounded queue's overflow policy.
+#[derive(Default)]
+pub struct Subscriptions {
+    dropped: Mutex<HashMap<String, Arc<AtomicUsize>>>,
+}
+
+impl Subscriptions {
+    fn counter_for(&self, topic: &str) -> Arc<AtomicUsize> {
+        self.dropped
+            .lock()
+            .unwrap()
+            .entry(topic.into())
+            .or_insert_with(|| Arc::new(AtomicUsize::new(0)))
+            .clone()
+    }
+
+    pub fn dropped(&self, topic: &str) -> usize {
+        self.dropped
+            .lock()
+            .unwrap()
+            .get(topic)
+            .map(|counter| counter.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn counter(&self, topic: &str) -> Arc<AtomicUsize> {
+        self.counter_for(topic)
+    }
+}
+
+/// Dispatches incoming messages for one subscription: a bounded queue
+/// feeds a single popper thread, which hands each message to the shared
+/// [`WorkerPool`] instead of running the callback itself. Returned so
+/// `Ros::subscribe_with_opts` can push messages into it from the
+/// underlying raw network callback.
+pub(crate) struct QueuedSubscription<T> {
+    queue: Arc<BoundedQueue<T>>,
+}
+
+impl<T: Send + 'static> QueuedSubscription<T> {
+    pub(crate) fn new<F>(
+        opts: SubscribeOptions,
+        dropped: Arc<AtomicUsize>,
+        pool: Arc<WorkerPool>,
+        callback: F,
+    ) -> QueuedSubscription<T>
+    where
+        F: Fn(T) -> () + Send + Sync + 'static,
+    {
+        let queue = Arc::new(BoundedQueue::<T>::new(opts.queue_size, opts.policy, dropped));
+        let callback = Arc::new(callback);
+
+        let dispatch_queue = queue.clone();
+        thread::spawn(move || loop {
+            let msg = dispatch_queue.pop();
+            let callback = callback.clone();
+            pool.execute(move || callback(msg));
+        });
+
+        QueuedSubscription { queue: queue }
+    }
+
+    pub(crate) fn push(&self, msg: T) {
+        self.queue.push(msg);
+    }
+}
+
+/// An outbound bounded queue: messages passed to `send` are buffered
+/// according to `opts` and forwarded to the underlying `PublisherStream`
+/// by a single background thread, so a slow publish call applies
+/// backpressure (or drops) instead of blocking the caller directly.
+pub struct BoundedPublisherStream<T: Message> {
+    queue: Arc<BoundedQueueHandle<T>>,
+}
+
+struct BoundedQueueHandle<T> {
+    queue: BoundedQueue<T>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl<T: Message + Send + 'static> BoundedPublisherStream<T> {
+    pub(crate) fn new(stream: PublisherStream<T>, opts: SubscribeOptions) -> BoundedPublisherStream<T> {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let handle = Arc::new(BoundedQueueHandle {
+            queue: BoundedQueue::new(opts.queue_size, opts.policy, dropped.clone()),
+            dropped: dropped,
+        });
+
+        let worker_handle = handle.clone();
+        thread::spawn(move || loop {
+            let msg = worker_handle.queue.pop();
+            if let Err(err) = stream.send(msg) {
+                error!("Failed to send queued publication: {}", err);
+            }
+        });
+
+        BoundedPublisherStream { queue: handle }
+    }
+
+    pub fn send(&self, msg: T) {
+        self.queue.queue.push(msg);
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn dropped_counter() -> Arc<AtomicUsize> {
+        Arc::new(AtomicUsize::new(0))
+    }
+
+    #[test]
+    fn drop_oldest_evicts_the_oldest_buffered_message() {
+        let dropped = dropped_counter();
+        let queue = BoundedQueue::new(2, OverflowPolicy::DropOldest, dropped.clone());
+        queue.push(1);
+        queue.push(2);
+        queue.push(3);
+
+        assert_eq!(queue.pop(), 2);
+        assert_eq!(queue.pop(), 3);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_oldest_buffered_messages() {
+        let dropped = dropped_counter();
+        let queue = BoundedQueue::new(2, OverflowPolicy::DropNewest, dropped.clone());
+        queue.push(1);
+        queue.push(2);
+        queue.push(3);
+
+        assert_eq!(queue.pop(), 1);
+        assert_eq!(queue.pop(), 2);
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn block_policy_blocks_the_pusher_until_a_slot_frees() {
+        let dropped = dropped_counter();
+        let queue = Arc::new(BoundedQueue::new(1, OverflowPolicy::Block, dropped));
+        queue.push(1);
+
+        let pushed = Arc::new(AtomicUsize::new(0));
+        let blocked_push = queue.clone();
+        let flag = pushed.clone();
+        let pusher = thread::spawn(move || {
+            blocked_push.push(2);
+            flag.store(1, Ordering::SeqCst);
+        });
+
+        // The queue is already full, so the second push should still be
+        // waiting on `not_full` rather than having dropped anything.
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(pushed.load(Ordering::SeqCst), 0);
+
+        assert_eq!(queue.pop(), 1);
+        pusher.join().unwrap();
+        assert_eq!(pushed.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.pop(), 2);
+    }
+
+    #[test]
+    fn worker_pool_execute_blocks_until_a_slot_frees() {
+        let pool = Arc::new(WorkerPool::new(1));
+        let (release_tx, release_rx) = ::std::sync::mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+
+        let rx = release_rx.clone();
+        pool.execute(move || {
+            let _ = rx.lock().unwrap().recv();
+        });
+
+        // The single worker thread is occupied; a second job should queue
+        // up behind it rather than spinning up unboundedly, so submitting
+        // it from a helper thread must still be blocked shortly after.
+        let second_submitted = Arc::new(AtomicUsize::new(0));
+        let flag = second_submitted.clone();
+        let submitter_pool = pool.clone();
+        let submitter = thread::spawn(move || {
+            submitter_pool.execute(move || {
+                flag.store(1, Ordering::SeqCst);
+            });
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(second_submitted.load(Ordering::SeqCst), 0);
+
+        release_tx.send(()).unwrap();
+        submitter.join().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(second_submitted.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,186 @@
+use std::cmp::min;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json;
+
+use super::api::error::{ErrorKind, Result};
+use super::api::master::{Master, SystemState, TopicData};
+use super::api::slave::Slave;
+
+/// Reported to the callback passed to `Ros::set_master_reconnect` after
+/// every ping of the master; `Lost` is reported once per outage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MasterStatus {
+    Connected,
+    Lost,
+    Reestablished,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    pub retry_interval: Duration,
+    pub max_backoff: Duration,
+    pub persist_path: Option<PathBuf>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            retry_interval: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            persist_path: None,
+        }
+    }
+}
+
+/// Every registration the node has made with the master, kept around so
+/// they can be replayed after the master restarts or a dropped connection
+/// comes back. Populated by `Ros::subscribe`/`publish`/`service` (and their
+/// raw, queued and dynamic variants) as a side effect of a successful
+/// registration.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Registrations {
+    publishers: Vec<(String, String)>,
+    subscribers: Vec<(String, String)>,
+    services: Vec<(String, String)>,
+}
+
+impl Registrations {
+    pub(crate) fn add_publisher(&mut self, name: &str, msg_type: &str) {
+        self.publishers.push((name.into(), msg_type.into()));
+    }
+
+    pub(crate) fn add_subscriber(&mut self, name: &str, msg_type: &str) {
+        self.subscribers.push((name.into(), msg_type.into()));
+    }
+
+    pub(crate) fn add_service(&mut self, name: &str, api: &str) {
+        self.services.push((name.into(), api.into()));
+    }
+
+    fn save(&self, path: &PathBuf) -> Result<()> {
+        let file = File::create(path).map_err(ErrorKind::from)?;
+        serde_json::to_writer(file, self).map_err(ErrorKind::from)?;
+        Ok(())
+    }
+}
+
+/// Whether every registration this node is supposed to hold still shows up
+/// against `name` in a freshly fetched `SystemState`. A `roscore` restart
+/// between two pings answers `get_system_state` with `Ok` both times, so a
+/// plain connection-error check never notices that the master forgot
+/// about us; comparing the node's own registrations against what the
+/// master actually reports is the only way to catch that.
+fn registrations_survived(state: &SystemState, name: &str, registrations: &Registrations) -> bool {
+    fn all_present(entries: &[TopicData], expected: &[(String, String)], name: &str) -> bool {
+        expected.iter().all(|&(ref topic, _)| {
+            entries
+                .iter()
+                .find(|entry| &entry.name == topic)
+                .map(|entry| entry.connections.iter().any(|node| node == name))
+                .unwrap_or(false)
+        })
+    }
+    all_present(&state.publishers, &registrations.publishers, name) &&
+        all_present(&state.subscribers, &registrations.subscribers, name) &&
+        all_present(&state.services, &registrations.services, name)
+}
+
+/// Re-registers every stored registration with the master. For
+/// subscribers, `register_subscriber` hands back the topic's current
+/// publisher list, and that list has to be fed to `slave` via
+/// `add_publishers_to_subscription` (exactly as `Ros::subscribe` does on
+/// first registration) — otherwise the master knows about the subscriber
+/// again, but it never reconnects to the (possibly now different) TCPROS
+/// publishers and the topic's data flow stays dead.
+fn replay(master: &Master, slave: &Slave, registrations: &Registrations) {
+    for &(ref name, ref msg_type) in &registrations.publishers {
+        if let Err(err) = master.register_publisher(name, msg_type) {
+            error!("Failed to re-register publisher '{}': {}", name, err);
+        }
+    }
+    for &(ref name, ref msg_type) in &registrations.subscribers {
+        match master.register_subscriber(name, msg_type) {
+            Ok(publishers) => {
+                if let Err(err) = slave.add_publishers_to_subscription(name, publishers.into_iter()) {
+                    error!(
+                        "Failed to reconnect subscriber '{}' to its publishers after replay: {}",
+                        name,
+                        err
+                    );
+                }
+            }
+            Err(err) => error!("Failed to re-register subscriber '{}': {}", name, err),
+        }
+    }
+    for &(ref name, ref api) in &registrations.services {
+        if let Err(err) = master.register_service(name, api) {
+            error!("Failed to re-register service '{}': {}", name, err);
+        }
+    }
+}
+
+/// Periodically pings the master and, on detecting a reconnect, replays
+/// every stored registration. Runs until the process exits; there's no
+/// handle to stop it early, matching `subscribe`/`publish`'s own
+/// fire-and-forget lifetime.
+pub(crate) fn spawn<F>(
+    master: Master,
+    slave: Slave,
+    name: String,
+    registrations: Arc<Mutex<Registrations>>,
+    policy: ReconnectPolicy,
+    on_status: F,
+) where
+    F: Fn(MasterStatus) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut backoff = policy.retry_interval;
+        let mut connected = true;
+        loop {
+            thread::sleep(backoff);
+
+            if let Some(ref path) = policy.persist_path {
+                if let Err(err) = registrations.lock().unwrap().save(path) {
+                    error!("Failed to persist registrations to '{}': {}", path.display(), err);
+                }
+            }
+
+            match master.get_system_state() {
+                Ok(state) => {
+                    let registrations = registrations.lock().unwrap();
+                    let survived = registrations_survived(&state, &name, &registrations);
+                    if !connected || !survived {
+                        if !connected {
+                            info!("Master connection reestablished, replaying registrations");
+                        } else {
+                            info!(
+                                "Master registrations for '{}' vanished without a connection \
+                                 error (master likely restarted), replaying registrations",
+                                name
+                            );
+                        }
+                        replay(&master, &slave, &registrations);
+                        on_status(MasterStatus::Reestablished);
+                        connected = true;
+                    } else {
+                        on_status(MasterStatus::Connected);
+                    }
+                    backoff = policy.retry_interval;
+                }
+                Err(err) => {
+                    if connected {
+                        error!("Lost connection to master: {}", err);
+                        on_status(MasterStatus::Lost);
+                        connected = false;
+                    }
+                    backoff = min(backoff * 2, policy.max_backoff);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use serde_cbor;
+
+use super::api::error::{ErrorKind, Result};
+use super::api::raw_message::RawPublisherStream;
+use super::api::ros::Ros;
+
+/// A single captured message, type-agnostic: `payload_bytes` is the raw
+/// wire-format body, so playback never needs the concrete Rust struct.
+#[derive(Serialize, Deserialize, Clone)]
+struct Frame {
+    topic: String,
+    msg_type: String,
+    md5sum: String,
+    receive_time_ns: u64,
+    payload_bytes: Vec<u8>,
+}
+
+fn write_frame<W: Write>(writer: &mut W, frame: &Frame) -> Result<()> {
+    let encoded = serde_cbor::to_vec(frame).map_err(ErrorKind::from)?;
+    writer
+        .write_all(&(encoded.len() as u32).to_le_bytes())
+        .map_err(ErrorKind::from)?;
+    writer.write_all(&encoded).map_err(ErrorKind::from)?;
+    Ok(())
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> Result<Option<Frame>> {
+    let mut len_bytes = [0u8; 4];
+    match reader.read_exact(&mut len_bytes) {
+        Ok(()) => {}
+        Err(ref err) if err.kind() == ::std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(ErrorKind::from(err).into()),
+    }
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    let mut buffer = vec![0u8; len];
+    reader.read_exact(&mut buffer).map_err(ErrorKind::from)?;
+    let frame = serde_cbor::from_slice(&buffer).map_err(ErrorKind::from)?;
+    Ok(Some(frame))
+}
+
+/// Subscribes (raw) to a fixed set of topics and appends every message
+/// received to a single file as a length-prefixed CBOR [`Frame`].
+pub struct Recorder {
+    writer: Arc<Mutex<BufWriter<File>>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn record(ros: &mut Ros, topics: &[(&str, &str, &str)], path: &Path) -> Result<Recorder> {
+        let file = File::create(path).map_err(ErrorKind::from)?;
+        let writer = Arc::new(Mutex::new(BufWriter::new(file)));
+        let recorder = Recorder {
+            writer: writer.clone(),
+            start: Instant::now(),
+        };
+
+        for &(topic, msg_type, md5sum) in topics {
+            let writer = writer.clone();
+            let start = recorder.start;
+            let topic_owned = topic.to_owned();
+            let msg_type_owned = msg_type.to_owned();
+            let md5sum_owned = md5sum.to_owned();
+            ros.subscribe_raw(topic, msg_type, move |raw| {
+                let frame = Frame {
+                    topic: topic_owned.clone(),
+                    msg_type: msg_type_owned.clone(),
+                    md5sum: md5sum_owned.clone(),
+                    receive_time_ns: start.elapsed().as_nanos() as u64,
+                    payload_bytes: raw.data,
+                };
+                if let Err(err) = write_frame(&mut *writer.lock().unwrap(), &frame) {
+                    error!("Failed to record message on '{}': {}", topic_owned, err);
+                }
+            })?;
+        }
+
+        Ok(recorder)
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.writer.lock().unwrap().flush().map_err(
+            ErrorKind::from,
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct PlayOptions {
+    pub rate: f64,
+    pub looping: bool,
+}
+
+impl PlayOptions {
+    pub fn new() -> PlayOptions {
+        PlayOptions {
+            rate: 1.0,
+            looping: false,
+        }
+    }
+}
+
+/// Reads back a capture made by [`Recorder`] and republishes it with the
+/// original inter-message timing preserved, without needing the concrete
+/// message types compiled in.
+pub struct Player {
+    frames: Vec<Frame>,
+}
+
+impl Player {
+    pub fn open(path: &Path) -> Result<Player> {
+        let mut reader = BufReader::new(File::open(path).map_err(ErrorKind::from)?);
+        let mut frames = Vec::new();
+        while let Some(frame) = read_frame(&mut reader)? {
+            frames.push(frame);
+        }
+        frames.sort_by_key(|frame| frame.receive_time_ns);
+        Ok(Player { frames })
+    }
+
+    /// Republishes every frame on `ros`, remapping topic names via
+    /// `remap` when provided. Sleeps use a monotonic clock and the delta
+    /// between consecutive frames, so absolute wall-clock drift during
+    /// playback never desyncs the relative timing.
+    pub fn play(&self, ros: &mut Ros, remap: &HashMap<String, String>, opts: PlayOptions) -> Result<()> {
+        let rate = if opts.rate > 0.0 { opts.rate } else { 1.0 };
+        // Advertise each remapped topic once and keep the resulting stream
+        // around; re-running `publish_raw` per frame would re-register
+        // the publisher with the master on every single message.
+        let mut streams: HashMap<String, RawPublisherStream> = HashMap::new();
+        let mut advertised_md5: HashMap<String, String> = HashMap::new();
+
+        loop {
+            let mut last_time_ns: Option<u64> = None;
+
+            for frame in &self.frames {
+                if let Some(last) = last_time_ns {
+                    let delta_ns = frame.receive_time_ns.saturating_sub(last);
+                    let scaled = Duration::from_nanos((delta_ns as f64 / rate) as u64);
+                    sleep(scaled);
+                }
+                last_time_ns = Some(frame.receive_time_ns);
+
+                let topic = remap.get(&frame.topic).cloned().unwrap_or_else(
+                    || frame.topic.clone(),
+                );
+
+                if let Some(existing) = advertised_md5.get(&topic) {
+                    if existing != &frame.md5sum {
+                        error!(
+                            "Skipping record for '{}': md5sum '{}' conflicts with already-advertised '{}'",
+                            topic,
+                            frame.md5sum,
+                            existing
+                        );
+                        continue;
+                    }
+                } else {
+                    advertised_md5.insert(topic.clone(), frame.md5sum.clone());
+                }
+
+                if !streams.contains_key(&topic) {
+                    let stream = ros.publish_raw(&topic, &frame.msg_type, &frame.md5sum)?;
+                    streams.insert(topic.clone(), stream);
+                }
+                streams.get(&topic).unwrap().send(&frame.payload_bytes)?;
+            }
+
+            if !opts.looping {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,695 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::{Cursor, Read, Write};
+use std::sync::Arc;
+
+use serde_json::{self, Value};
+
+use super::error::Result;
+use super::genmsg;
+use super::helpers;
+use super::helpers::MessageMap;
+
+/// Search paths for runtime message resolution, mirroring
+/// `depend_on_messages`'s `CMAKE_PREFIX_PATH`/`ROSRUST_MSG_PATH` handling,
+/// but read at call time rather than once in `build.rs`.
+pub fn resolve_search_paths() -> Vec<String> {
+    let cmake_paths = env::var("CMAKE_PREFIX_PATH")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|v| !v.is_empty())
+        .map(|v| format!("{}/share", v))
+        .collect::<Vec<String>>();
+    let extra_paths = env::var("ROSRUST_MSG_PATH")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect::<Vec<String>>();
+    cmake_paths.into_iter().chain(extra_paths).collect()
+}
+
+/// The shape of one message type, parsed from its `.msg` definition text
+/// at runtime instead of emitted as Rust source by `build.rs`. Nested
+/// message fields embed their own resolved `MessageSchema`, so encoding
+/// and decoding never need to touch the filesystem again once a schema
+/// has been resolved.
+#[derive(Clone, Debug)]
+pub struct MessageSchema {
+    pub msg_type: String,
+    pub md5sum: String,
+    pub definition: String,
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FieldSchema {
+    pub name: String,
+    pub kind: FieldType,
+}
+
+#[derive(Clone, Debug)]
+pub enum FieldType {
+    Bool,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    String,
+    Time,
+    Duration,
+    Message(Arc<MessageSchema>),
+    Array(Box<FieldType>, Option<usize>),
+}
+
+/// Resolves `msg_type` (e.g. `"sensor_msgs/Imu"`) against `folders`
+/// (typically derived from `ROSRUST_MSG_PATH`/`CMAKE_PREFIX_PATH`, same
+/// as `depend_on_messages`), computing its md5sum and field layout the
+/// same way the build-time code generator does.
+pub fn resolve(folders: &[&str], msg_type: &str) -> Result<Arc<MessageSchema>> {
+    let (message_map, hashes) = genmsg::resolve_message_map(folders, &[msg_type])?;
+    let mut cache = HashMap::new();
+    build_schema(&message_map, &hashes, msg_type, &mut cache)
+}
+
+fn build_schema(
+    message_map: &MessageMap,
+    hashes: &HashMap<(String, String), String>,
+    msg_type: &str,
+    cache: &mut HashMap<String, Arc<MessageSchema>>,
+) -> Result<Arc<MessageSchema>> {
+    if let Some(schema) = cache.get(msg_type) {
+        return Ok(schema.clone());
+    }
+
+    let (package, name) = split_type(msg_type)?;
+    let key = (package, name);
+    let message = match message_map.messages.get(&key) {
+        Some(message) => message,
+        None => bail!("Unknown message type '{}'", msg_type),
+    };
+    let definition = helpers::generate_message_definition(&message_map.messages, message)?;
+    let md5sum = match hashes.get(&key) {
+        Some(hash) => hash.clone(),
+        None => bail!("No md5sum computed for '{}'", msg_type),
+    };
+    let package = key.0.clone();
+
+    // Reserve the slot before recursing, so a message that (directly or
+    // transitively) refers to itself resolves to the same Arc rather than
+    // looping forever.
+    let schema = Arc::new(MessageSchema {
+        msg_type: msg_type.into(),
+        md5sum: md5sum,
+        definition: definition.clone(),
+        fields: Vec::new(),
+    });
+    cache.insert(msg_type.into(), schema.clone());
+
+    let fields = parse_fields(&definition, &package, message_map, hashes, cache)?;
+
+    // `schema` above is only a placeholder for cycle-breaking; build the
+    // real value now that fields are known and swap it into the cache.
+    let schema = Arc::new(MessageSchema {
+        msg_type: msg_type.into(),
+        md5sum: schema.md5sum.clone(),
+        definition: schema.definition.clone(),
+        fields: fields,
+    });
+    cache.insert(msg_type.into(), schema.clone());
+    Ok(schema)
+}
+
+fn parse_fields(
+    definition: &str,
+    package: &str,
+    message_map: &MessageMap,
+    hashes: &HashMap<(String, String), String>,
+    cache: &mut HashMap<String, Arc<MessageSchema>>,
+) -> Result<Vec<FieldSchema>> {
+    let mut fields = Vec::new();
+    for raw_line in definition.lines() {
+        // Definitions for dependencies are appended after a banner line;
+        // only the leading lines describe this message's own fields.
+        if raw_line.starts_with("==========") {
+            break;
+        }
+        let line = match raw_line.find('#') {
+            Some(idx) => &raw_line[..idx],
+            None => raw_line,
+        }.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let type_str = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim();
+        if rest.is_empty() || rest.contains('=') {
+            // Blank, or a constant declaration (e.g. `int32 FOO=1`),
+            // neither of which is an instance field.
+            continue;
+        }
+        fields.push(FieldSchema {
+            name: rest.into(),
+            kind: parse_type(type_str, package, message_map, hashes, cache)?,
+        });
+    }
+    Ok(fields)
+}
+
+fn parse_type(
+    raw: &str,
+    package: &str,
+    message_map: &MessageMap,
+    hashes: &HashMap<(String, String), String>,
+    cache: &mut HashMap<String, Arc<MessageSchema>>,
+) -> Result<FieldType> {
+    if let Some(idx) = raw.find('[') {
+        let base = &raw[..idx];
+        let len_str = raw[idx + 1..].trim_end_matches(']');
+        let fixed = if len_str.is_empty() {
+            None
+        } else {
+            Some(match len_str.parse() {
+                Ok(len) => len,
+                Err(_) => bail!("Invalid array length in '{}'", raw),
+            })
+        };
+        let element = parse_type(base, package, message_map, hashes, cache)?;
+        return Ok(FieldType::Array(Box::new(element), fixed));
+    }
+    Ok(match raw {
+        "bool" => FieldType::Bool,
+        "int8" | "byte" => FieldType::I8,
+        "uint8" | "char" => FieldType::U8,
+        "int16" => FieldType::I16,
+        "uint16" => FieldType::U16,
+        "int32" => FieldType::I32,
+        "uint32" => FieldType::U32,
+        "int64" => FieldType::I64,
+        "uint64" => FieldType::U64,
+        "float32" => FieldType::F32,
+        "float64" => FieldType::F64,
+        "string" => FieldType::String,
+        "time" => FieldType::Time,
+        "duration" => FieldType::Duration,
+        "Header" => FieldType::Message(build_schema(message_map, hashes, "std_msgs/Header", cache)?),
+        // Same-package references are written unqualified in `.msg` files
+        // (e.g. `Point position` inside `geometry_msgs`); qualify with the
+        // enclosing message's package before resolving, same as `roslib`'s
+        // own message generator does.
+        other if other.contains('/') => FieldType::Message(build_schema(message_map, hashes, other, cache)?),
+        other => {
+            let qualified = format!("{}/{}", package, other);
+            FieldType::Message(build_schema(message_map, hashes, &qualified, cache)?)
+        }
+    })
+}
+
+fn split_type(msg_type: &str) -> Result<(String, String)> {
+    let mut parts = msg_type.splitn(2, '/');
+    let package = match parts.next() {
+        Some(v) if !v.is_empty() => v,
+        _ => bail!("Message type needs to be in package/name format: {}", msg_type),
+    };
+    let name = match parts.next() {
+        Some(v) if !v.is_empty() => v,
+        _ => bail!("Message type needs to be in package/name format: {}", msg_type),
+    };
+    Ok((package.into(), name.into()))
+}
+
+/// A value for one field of a [`DynamicMessage`].
+#[derive(Clone, Debug)]
+pub enum FieldValue {
+    Bool(bool),
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Str(String),
+    Time(u32, u32),
+    Duration(i32, i32),
+    Message(DynamicMessage),
+    Array(Vec<FieldValue>),
+}
+
+/// A message value whose layout is only known at runtime, resolved via
+/// [`resolve`] instead of being a compiled Rust struct. Backs
+/// `Ros::subscribe_dynamic`/`publish_dynamic`, so the bridge and rosbag
+/// playback can work with message types that aren't linked into the
+/// binary.
+#[derive(Clone, Debug)]
+pub struct DynamicMessage {
+    schema: Arc<MessageSchema>,
+    fields: HashMap<String, FieldValue>,
+}
+
+impl DynamicMessage {
+    pub fn new(schema: Arc<MessageSchema>) -> DynamicMessage {
+        DynamicMessage {
+            schema: schema,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn schema(&self) -> &Arc<MessageSchema> {
+        &self.schema
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FieldValue> {
+        self.fields.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, value: FieldValue) {
+        self.fields.insert(name.into(), value);
+    }
+
+    pub fn get_i32(&self, name: &str) -> Option<i32> {
+        match self.fields.get(name) {
+            Some(&FieldValue::I32(v)) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn set_i32(&mut self, name: &str, value: i32) {
+        self.set(name, FieldValue::I32(value));
+    }
+
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        match self.fields.get(name) {
+            Some(&FieldValue::F64(v)) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn set_f64(&mut self, name: &str, value: f64) {
+        self.set(name, FieldValue::F64(value));
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.fields.get(name) {
+            Some(&FieldValue::Str(ref v)) => Some(v.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn set_str(&mut self, name: &str, value: &str) {
+        self.set(name, FieldValue::Str(value.into()));
+    }
+
+    pub fn decode(schema: Arc<MessageSchema>, bytes: &[u8]) -> Result<DynamicMessage> {
+        let mut cursor = Cursor::new(bytes);
+        decode_message(&mut cursor, &schema)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        for field in &self.schema.fields {
+            let value = match self.fields.get(&field.name) {
+                Some(value) => value,
+                None => bail!("Missing value for field '{}'", field.name),
+            };
+            encode_field(&mut buffer, value)?;
+        }
+        Ok(buffer)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut map = serde_json::Map::new();
+        for field in &self.schema.fields {
+            if let Some(value) = self.fields.get(&field.name) {
+                map.insert(field.name.clone(), value_to_json(value));
+            }
+        }
+        Value::Object(map)
+    }
+
+    pub fn from_json(schema: Arc<MessageSchema>, value: &Value) -> Result<DynamicMessage> {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => bail!("Expected a JSON object for message '{}'", schema.msg_type),
+        };
+        let mut message = DynamicMessage::new(schema.clone());
+        for field in &schema.fields {
+            let json_value = match object.get(&field.name) {
+                Some(value) => value,
+                None => bail!("Missing field '{}' in JSON for '{}'", field.name, schema.msg_type),
+            };
+            let value = json_to_value(&field.kind, json_value)?;
+            message.fields.insert(field.name.clone(), value);
+        }
+        Ok(message)
+    }
+}
+
+fn decode_message<R: Read>(reader: &mut R, schema: &Arc<MessageSchema>) -> Result<DynamicMessage> {
+    let mut message = DynamicMessage::new(schema.clone());
+    for field in &schema.fields {
+        let value = decode_field(reader, &field.kind)?;
+        message.fields.insert(field.name.clone(), value);
+    }
+    Ok(message)
+}
+
+fn decode_field<R: Read>(reader: &mut R, kind: &FieldType) -> Result<FieldValue> {
+    Ok(match *kind {
+        FieldType::Bool => FieldValue::Bool(read_u8(reader)? != 0),
+        FieldType::I8 => FieldValue::I8(read_u8(reader)? as i8),
+        FieldType::U8 => FieldValue::U8(read_u8(reader)?),
+        FieldType::I16 => FieldValue::I16(read_u16(reader)? as i16),
+        FieldType::U16 => FieldValue::U16(read_u16(reader)?),
+        FieldType::I32 => FieldValue::I32(read_u32(reader)? as i32),
+        FieldType::U32 => FieldValue::U32(read_u32(reader)?),
+        FieldType::I64 => FieldValue::I64(read_u64(reader)? as i64),
+        FieldType::U64 => FieldValue::U64(read_u64(reader)?),
+        FieldType::F32 => FieldValue::F32(f32::from_bits(read_u32(reader)?)),
+        FieldType::F64 => FieldValue::F64(f64::from_bits(read_u64(reader)?)),
+        FieldType::String => {
+            let len = read_u32(reader)? as usize;
+            let mut buf = vec![0u8; len];
+            reader.read_exact(&mut buf)?;
+            FieldValue::Str(String::from_utf8(buf).map_err(|err| err.utf8_error())?)
+        }
+        FieldType::Time => FieldValue::Time(read_u32(reader)?, read_u32(reader)?),
+        FieldType::Duration => FieldValue::Duration(read_u32(reader)? as i32, read_u32(reader)? as i32),
+        FieldType::Message(ref schema) => FieldValue::Message(decode_message(reader, schema)?),
+        FieldType::Array(ref element, fixed) => {
+            let len = match fixed {
+                Some(len) => len,
+                None => read_u32(reader)? as usize,
+            };
+            let mut items = Vec::with_capacity(len);
+            for _ in 0..len {
+                items.push(decode_field(reader, element)?);
+            }
+            FieldValue::Array(items)
+        }
+    })
+}
+
+fn encode_field<W: Write>(writer: &mut W, value: &FieldValue) -> Result<()> {
+    match *value {
+        FieldValue::Bool(v) => writer.write_all(&[v as u8])?,
+        FieldValue::I8(v) => writer.write_all(&[v as u8])?,
+        FieldValue::U8(v) => writer.write_all(&[v])?,
+        FieldValue::I16(v) => writer.write_all(&(v as u16).to_le_bytes())?,
+        FieldValue::U16(v) => writer.write_all(&v.to_le_bytes())?,
+        FieldValue::I32(v) => writer.write_all(&(v as u32).to_le_bytes())?,
+        FieldValue::U32(v) => writer.write_all(&v.to_le_bytes())?,
+        FieldValue::I64(v) => writer.write_all(&(v as u64).to_le_bytes())?,
+        FieldValue::U64(v) => writer.write_all(&v.to_le_bytes())?,
+        FieldValue::F32(v) => writer.write_all(&v.to_bits().to_le_bytes())?,
+        FieldValue::F64(v) => writer.write_all(&v.to_bits().to_le_bytes())?,
+        FieldValue::Str(ref v) => {
+            writer.write_all(&(v.len() as u32).to_le_bytes())?;
+            writer.write_all(v.as_bytes())?;
+        }
+        FieldValue::Time(secs, nsecs) => {
+            writer.write_all(&secs.to_le_bytes())?;
+            writer.write_all(&nsecs.to_le_bytes())?;
+        }
+        FieldValue::Duration(secs, nsecs) => {
+            writer.write_all(&(secs as u32).to_le_bytes())?;
+            writer.write_all(&(nsecs as u32).to_le_bytes())?;
+        }
+        FieldValue::Message(ref message) => {
+            for field in &message.schema.fields {
+                let field_value = match message.fields.get(&field.name) {
+                    Some(value) => value,
+                    None => bail!("Missing value for field '{}'", field.name),
+                };
+                encode_field(writer, field_value)?;
+            }
+        }
+        FieldValue::Array(ref items) => {
+            writer.write_all(&(items.len() as u32).to_le_bytes())?;
+            for item in items {
+                encode_field(writer, item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn value_to_json(value: &FieldValue) -> Value {
+    match *value {
+        FieldValue::Bool(v) => Value::Bool(v),
+        FieldValue::I8(v) => v.into(),
+        FieldValue::U8(v) => v.into(),
+        FieldValue::I16(v) => v.into(),
+        FieldValue::U16(v) => v.into(),
+        FieldValue::I32(v) => v.into(),
+        FieldValue::U32(v) => v.into(),
+        FieldValue::I64(v) => v.into(),
+        FieldValue::U64(v) => v.into(),
+        FieldValue::F32(v) => (v as f64).into(),
+        FieldValue::F64(v) => v.into(),
+        FieldValue::Str(ref v) => Value::String(v.clone()),
+        FieldValue::Time(secs, nsecs) => json!({ "secs": secs, "nsecs": nsecs }),
+        FieldValue::Duration(secs, nsecs) => json!({ "secs": secs, "nsecs": nsecs }),
+        FieldValue::Message(ref message) => message.to_json(),
+        FieldValue::Array(ref items) => Value::Array(items.iter().map(value_to_json).collect()),
+    }
+}
+
+fn json_to_value(kind: &FieldType, value: &Value) -> Result<FieldValue> {
+    Ok(match *kind {
+        FieldType::Bool => FieldValue::Bool(value.as_bool().ok_or_else(|| "Expected a bool")?),
+        FieldType::I8 => FieldValue::I8(json_as_i64(value)? as i8),
+        FieldType::U8 => FieldValue::U8(json_as_i64(value)? as u8),
+        FieldType::I16 => FieldValue::I16(json_as_i64(value)? as i16),
+        FieldType::U16 => FieldValue::U16(json_as_i64(value)? as u16),
+        FieldType::I32 => FieldValue::I32(json_as_i64(value)? as i32),
+        FieldType::U32 => FieldValue::U32(json_as_i64(value)? as u32),
+        FieldType::I64 => FieldValue::I64(json_as_i64(value)?),
+        FieldType::U64 => FieldValue::U64(json_as_u64(value)?),
+        FieldType::F32 => FieldValue::F32(json_as_f64(value)? as f32),
+        FieldType::F64 => FieldValue::F64(json_as_f64(value)?),
+        FieldType::String => FieldValue::Str(value.as_str().ok_or_else(|| "Expected a string")?.into()),
+        FieldType::Time => FieldValue::Time(json_field_u32(value, "secs")?, json_field_u32(value, "nsecs")?),
+        FieldType::Duration => {
+            FieldValue::Duration(json_field_u32(value, "secs")? as i32, json_field_u32(value, "nsecs")? as i32)
+        }
+        FieldType::Message(ref schema) => FieldValue::Message(DynamicMessage::from_json(schema.clone(), value)?),
+        FieldType::Array(ref element, _) => {
+            let items = value.as_array().ok_or_else(|| "Expected a JSON array")?;
+            let mut values = Vec::with_capacity(items.len());
+            for item in items {
+                values.push(json_to_value(element, item)?);
+            }
+            FieldValue::Array(values)
+        }
+    })
+}
+
+fn json_as_i64(value: &Value) -> Result<i64> {
+    match value.as_i64() {
+        Some(v) => Ok(v),
+        None => bail!("Expected an integer, got '{}'", value),
+    }
+}
+
+fn json_as_u64(value: &Value) -> Result<u64> {
+    match value.as_u64() {
+        Some(v) => Ok(v),
+        None => bail!("Expected an unsigned integer, got '{}'", value),
+    }
+}
+
+fn json_as_f64(value: &Value) -> Result<f64> {
+    match value.as_f64() {
+        Some(v) => Ok(v),
+        None => bail!("Expected a number, got '{}'", value),
+    }
+}
+
+fn json_field_u32(value: &Value, field: &str) -> Result<u32> {
+    match value.get(field).and_then(Value::as_u64) {
+        Some(v) => Ok(v as u32),
+        None => bail!("Expected field '{}' on '{}'", field, value),
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(msg_type: &str, fields: Vec<FieldSchema>) -> Arc<MessageSchema> {
+        Arc::new(MessageSchema {
+            msg_type: msg_type.into(),
+            md5sum: String::new(),
+            definition: String::new(),
+            fields: fields,
+        })
+    }
+
+    fn sample_schema() -> Arc<MessageSchema> {
+        let point = schema(
+            "geometry_msgs/Point",
+            vec![
+                FieldSchema { name: "x".into(), kind: FieldType::F64 },
+                FieldSchema { name: "y".into(), kind: FieldType::F64 },
+            ],
+        );
+        schema(
+            "test_msgs/Sample",
+            vec![
+                FieldSchema { name: "flag".into(), kind: FieldType::Bool },
+                FieldSchema { name: "small".into(), kind: FieldType::I8 },
+                FieldSchema { name: "raw".into(), kind: FieldType::U8 },
+                FieldSchema { name: "label".into(), kind: FieldType::String },
+                FieldSchema { name: "position".into(), kind: FieldType::Message(point) },
+                FieldSchema {
+                    name: "samples".into(),
+                    kind: FieldType::Array(Box::new(FieldType::I32), None),
+                },
+            ],
+        )
+    }
+
+    fn sample_message(schema: Arc<MessageSchema>) -> DynamicMessage {
+        let mut point = DynamicMessage::new(match schema.fields[4].kind {
+            FieldType::Message(ref schema) => schema.clone(),
+            _ => unreachable!(),
+        });
+        point.set_f64("x", 1.5);
+        point.set_f64("y", -2.5);
+
+        let mut message = DynamicMessage::new(schema);
+        message.set("flag", FieldValue::Bool(true));
+        // -6 only round-trips through the wire as the same value if it is
+        // encoded/decoded on the signed (I8) path rather than as U8.
+        message.set("small", FieldValue::I8(-6));
+        message.set("raw", FieldValue::U8(250));
+        message.set_str("label", "hello");
+        message.set("position", FieldValue::Message(point));
+        message.set(
+            "samples",
+            FieldValue::Array(vec![FieldValue::I32(1), FieldValue::I32(-2), FieldValue::I32(3)]),
+        );
+        message
+    }
+
+    fn as_i8(value: Option<&FieldValue>) -> i8 {
+        match value {
+            Some(&FieldValue::I8(v)) => v,
+            other => panic!("expected an I8 field, got {:?}", other),
+        }
+    }
+
+    fn as_u8(value: Option<&FieldValue>) -> u8 {
+        match value {
+            Some(&FieldValue::U8(v)) => v,
+            other => panic!("expected a U8 field, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let schema = sample_schema();
+        let message = sample_message(schema.clone());
+
+        let bytes = message.encode().unwrap();
+        let decoded = DynamicMessage::decode(schema, &bytes).unwrap();
+
+        assert_eq!(as_i8(decoded.get("small")), -6);
+        assert_eq!(as_u8(decoded.get("raw")), 250);
+        assert_eq!(decoded.get_str("label"), Some("hello"));
+        match decoded.get("position") {
+            Some(&FieldValue::Message(ref point)) => {
+                assert_eq!(point.get_f64("x"), Some(1.5));
+                assert_eq!(point.get_f64("y"), Some(-2.5));
+            }
+            other => panic!("expected a nested message, got {:?}", other),
+        }
+        match decoded.get("samples") {
+            Some(&FieldValue::Array(ref items)) => assert_eq!(items.len(), 3),
+            other => panic!("expected an array field, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn to_json_from_json_round_trip() {
+        let schema = sample_schema();
+        let message = sample_message(schema.clone());
+
+        let value = message.to_json();
+        let restored = DynamicMessage::from_json(schema, &value).unwrap();
+
+        assert_eq!(as_i8(restored.get("small")), -6);
+        assert_eq!(as_u8(restored.get("raw")), 250);
+        assert_eq!(restored.get_str("label"), Some("hello"));
+    }
+
+    #[test]
+    fn u64_round_trips_through_json_beyond_i64_max() {
+        let schema = schema(
+            "test_msgs/Big",
+            vec![FieldSchema { name: "value".into(), kind: FieldType::U64 }],
+        );
+        let mut message = DynamicMessage::new(schema.clone());
+        message.set("value", FieldValue::U64(u64::max_value()));
+
+        let value = message.to_json();
+        let restored = DynamicMessage::from_json(schema, &value).unwrap();
+
+        match restored.get("value") {
+            Some(&FieldValue::U64(v)) => assert_eq!(v, u64::max_value()),
+            other => panic!("expected U64, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn i8_and_u8_decode_on_distinct_signedness_paths() {
+        // `parse_type` maps the ROS aliases `byte`/`char` onto `I8`/`U8`
+        // (`byte` is signed, `char` is unsigned); this pins down that the
+        // two `FieldType`s really do decode the same byte differently, so
+        // a mixed-up alias mapping changes the observed value.
+        let bytes = [0xFAu8];
+        match decode_field(&mut Cursor::new(&bytes[..]), &FieldType::I8).unwrap() {
+            FieldValue::I8(v) => assert_eq!(v, -6),
+            other => panic!("expected I8, got {:?}", other),
+        }
+        match decode_field(&mut Cursor::new(&bytes[..]), &FieldType::U8).unwrap() {
+            FieldValue::U8(v) => assert_eq!(v, 250),
+            other => panic!("expected U8, got {:?}", other),
+        }
+    }
+}
@@ -1,17 +1,30 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use super::helpers;
+use super::helpers::MessageMap;
 use super::error::Result;
 
-pub fn depend_on_messages(folders: &[&str], messages: &[&str]) -> Result<String> {
-    let mut output = Vec::<String>::new();
-    output.push("#[macro_use]\nextern crate serde_derive;".into());
-    output.push("pub mod msg {".into());
+/// Parses and hashes a set of `package/Message` names, reusable by both
+/// the build-time code generator below and anything (e.g. a dynamic
+/// message resolver) that needs the parsed definitions without emitting
+/// Rust source.
+pub fn resolve_message_map(
+    folders: &[&str],
+    messages: &[&str],
+) -> Result<(MessageMap, HashMap<(String, String), String>)> {
     let mut message_pairs = Vec::<(&str, &str)>::new();
     for message in messages {
         message_pairs.push(string_into_pair(message)?);
     }
     let message_map = helpers::get_message_map(folders, &message_pairs)?;
     let hashes = helpers::calculate_md5(&message_map)?;
+    Ok((message_map, hashes))
+}
+
+pub fn depend_on_messages(folders: &[&str], messages: &[&str]) -> Result<String> {
+    let mut output = Vec::<String>::new();
+    output.push("#[macro_use]\nextern crate serde_derive;".into());
+    output.push("pub mod msg {".into());
+    let (message_map, hashes) = resolve_message_map(folders, messages)?;
     let packages = message_map
         .messages
         .iter()
@@ -0,0 +1,5 @@
+pub mod msg;
+pub mod helpers;
+pub mod error;
+pub mod genmsg;
+pub mod dynamic;